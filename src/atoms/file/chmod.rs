@@ -1,11 +1,20 @@
 use super::super::Atom;
 use super::FileAtom;
+use std::cell::RefCell;
 use std::path::PathBuf;
 use tracing::error;
 
 pub struct FilePermissions {
     path: PathBuf,
-    mode: u32,
+    mode: String,
+    recursive: bool,
+    excludes: Vec<String>,
+    // The permission bits every affected path had before the *first*
+    // plan()/execute() call, so revert() can restore them. `RefCell` lets
+    // plan()/execute() record this from behind the `&self` the Atom trait
+    // hands out; it stays `None` until the first snapshot so a later
+    // plan()-after-execute() doesn't clobber it with the already-changed mode.
+    last_modes: RefCell<Option<Vec<(PathBuf, u32)>>>,
 }
 
 impl FileAtom for FilePermissions {
@@ -25,39 +34,361 @@ impl std::fmt::Display for FilePermissions {
     }
 }
 
+// A single clause of a symbolic chmod spec, e.g. the `u+rwx` in `u+rwx,go-w`.
+struct ModeClause {
+    owner: bool,
+    group: bool,
+    other: bool,
+    op: char,
+    perms: String,
+}
+
+impl ModeClause {
+    // The bits this clause would set, given who it targets. `original` and
+    // `is_dir` describe the file *before* any clause in this spec ran, which
+    // is what chmod's capital `X` is evaluated against.
+    fn selected_bits(&self, original: u32, is_dir: bool) -> u32 {
+        let mut bits = 0u32;
+
+        for perm in self.perms.chars() {
+            match perm {
+                'r' => {
+                    if self.owner {
+                        bits |= 0o400;
+                    }
+                    if self.group {
+                        bits |= 0o040;
+                    }
+                    if self.other {
+                        bits |= 0o004;
+                    }
+                }
+                'w' => {
+                    if self.owner {
+                        bits |= 0o200;
+                    }
+                    if self.group {
+                        bits |= 0o020;
+                    }
+                    if self.other {
+                        bits |= 0o002;
+                    }
+                }
+                'x' => {
+                    if self.owner {
+                        bits |= 0o100;
+                    }
+                    if self.group {
+                        bits |= 0o010;
+                    }
+                    if self.other {
+                        bits |= 0o001;
+                    }
+                }
+                // Capital `X`: only sets execute when the target is a
+                // directory or already has an execute bit set somewhere,
+                // so a recursive `rwX` doesn't make every plain file +x.
+                'X' if is_dir || original & 0o111 != 0 => {
+                    if self.owner {
+                        bits |= 0o100;
+                    }
+                    if self.group {
+                        bits |= 0o010;
+                    }
+                    if self.other {
+                        bits |= 0o001;
+                    }
+                }
+                's' => {
+                    if self.owner {
+                        bits |= 0o4000;
+                    }
+                    if self.group {
+                        bits |= 0o2000;
+                    }
+                }
+                't' => bits |= 0o1000,
+                _ => {}
+            }
+        }
+
+        bits
+    }
+
+    // The bits this clause is allowed to touch, used to clear a triad for `=`.
+    fn clearable_bits(&self) -> u32 {
+        let mut bits = 0u32;
+
+        if self.owner {
+            bits |= 0o4700;
+        }
+        if self.group {
+            bits |= 0o2070;
+        }
+        if self.other {
+            bits |= 0o0007;
+        }
+        if self.perms.contains('t') {
+            bits |= 0o1000;
+        }
+
+        bits
+    }
+
+    fn apply(&self, mode: u32, original: u32, is_dir: bool) -> u32 {
+        let selected = self.selected_bits(original, is_dir);
+
+        match self.op {
+            '+' => mode | selected,
+            '-' => mode & !selected,
+            '=' => (mode & !self.clearable_bits()) | selected,
+            _ => mode,
+        }
+    }
+}
+
+fn parse_clause(raw: &str) -> anyhow::Result<ModeClause> {
+    let raw = raw.trim();
+    let op_idx = raw
+        .find(['+', '-', '='])
+        .ok_or_else(|| anyhow::anyhow!("Missing +/-/= operator in chmod clause '{}'", raw))?;
+
+    let who_part = &raw[..op_idx];
+    let op = raw.as_bytes()[op_idx] as char;
+    let perms_part = &raw[op_idx + 1..];
+
+    let (mut owner, mut group, mut other) = (false, false, false);
+
+    if who_part.is_empty() || who_part.contains('a') {
+        owner = true;
+        group = true;
+        other = true;
+    } else {
+        for c in who_part.chars() {
+            match c {
+                'u' => owner = true,
+                'g' => group = true,
+                'o' => other = true,
+                _ => anyhow::bail!("Unknown who specifier '{}' in clause '{}'", c, raw),
+            }
+        }
+    }
+
+    for c in perms_part.chars() {
+        if !"rwxXst".contains(c) {
+            anyhow::bail!("Unknown permission specifier '{}' in clause '{}'", c, raw);
+        }
+    }
+
+    Ok(ModeClause {
+        owner,
+        group,
+        other,
+        op,
+        perms: perms_part.to_string(),
+    })
+}
+
+// Parses a chmod-style mode spec (plain octal, or symbolic `u+rwx,go-w`)
+// into a closure that computes the target permission bits from the file's
+// current permission bits and whether it is a directory (needed for `X`).
+fn parse_mode_spec(spec: &str) -> anyhow::Result<Box<dyn Fn(u32, bool) -> u32 + Send + Sync>> {
+    let spec = spec.trim();
+
+    if !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit()) {
+        let absolute = u32::from_str_radix(spec, 8)
+            .map_err(|err| anyhow::anyhow!("Invalid octal mode '{}': {}", spec, err))?;
+
+        return Ok(Box::new(move |_current, _is_dir| absolute));
+    }
+
+    let clauses = spec
+        .split(',')
+        .map(parse_clause)
+        .collect::<anyhow::Result<Vec<ModeClause>>>()?;
+
+    Ok(Box::new(move |current, is_dir| {
+        clauses
+            .iter()
+            .fold(current, |mode, clause| clause.apply(mode, current, is_dir))
+    }))
+}
+
+// Walks `root` depth-first, returning every entry underneath it (files and
+// directories, not `root` itself) except those matched by `excludes`. Only
+// real directories are recursed into: a `DirEntry`'s `file_type()` is an
+// `lstat`, so a symlink to a directory is reported as a symlink rather than
+// followed, which would otherwise let a tree escape `root` (or cycle back
+// into an ancestor) through a symlinked subdirectory. The result is sorted
+// in reverse path order, so every entry comes before the directory that
+// contains it and recursive plan/execute runs deterministically.
+fn walk_tree(root: &std::path::Path, excludes: &[glob::Pattern]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if excludes.iter().any(|pattern| pattern.matches_path(&path)) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            entries.extend(walk_tree(&path, excludes)?);
+        }
+
+        entries.push(path);
+    }
+
+    // Reverse-sort: a descendant's path string is always a strict extension
+    // of its ancestor's, so this puts every entry ahead of any directory
+    // that contains it.
+    entries.sort_by(|a, b| b.cmp(a));
+
+    Ok(entries)
+}
+
+fn compile_excludes(excludes: &[String]) -> anyhow::Result<Vec<glob::Pattern>> {
+    excludes
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map_err(|err| anyhow::anyhow!("Invalid exclude glob '{}': {}", pattern, err))
+        })
+        .collect()
+}
+
 #[cfg(unix)]
 use std::os::unix::prelude::PermissionsExt;
 
+#[cfg(unix)]
+impl FilePermissions {
+    // Whether a single entry's permission bits differ from the spec's target.
+    fn entry_needs_change(
+        metadata: &std::fs::Metadata,
+        mode_fn: &(dyn Fn(u32, bool) -> u32 + Send + Sync),
+    ) -> bool {
+        let current_mode = metadata.permissions().mode();
+        // Derive the file-type bits from the actual metadata instead of
+        // assuming a regular file, so directories/symlinks plan correctly.
+        let type_bits = current_mode & 0o170000;
+        let target = mode_fn(current_mode & 0o7777, metadata.is_dir());
+
+        (type_bits | target) != current_mode
+    }
+
+    fn apply_to_entry(
+        path: &std::path::Path,
+        metadata: &std::fs::Metadata,
+        mode_fn: &(dyn Fn(u32, bool) -> u32 + Send + Sync),
+    ) -> anyhow::Result<()> {
+        let current = metadata.permissions().mode() & 0o7777;
+        let target = mode_fn(current, metadata.is_dir());
+
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(target))?;
+
+        Ok(())
+    }
+
+    // Every path this atom would touch, ordered so a directory always comes
+    // after everything beneath it: just `self.path`, or `self.path` plus its
+    // entire subtree when `recursive` is set (mirroring `chmod -R`, which
+    // changes the directory itself as well as its contents). Applying (or
+    // reverting) top-down instead can lock a directory before its children
+    // are done, breaking subsequent entries with "Permission denied".
+    fn target_paths(&self) -> anyhow::Result<Vec<PathBuf>> {
+        if !self.recursive {
+            return Ok(vec![self.path.clone()]);
+        }
+
+        let excludes = compile_excludes(&self.excludes)?;
+        let mut paths = walk_tree(&self.path, &excludes)?;
+        paths.push(self.path.clone());
+
+        Ok(paths)
+    }
+
+    // Records the current mode of every path in `paths`, but only the first
+    // time it's called for this atom: a `plan()` run after a successful
+    // `execute()` must not overwrite the pre-change snapshot with the
+    // already-applied mode.
+    fn snapshot_modes(&self, paths: &[PathBuf]) {
+        if self.last_modes.borrow().is_some() {
+            return;
+        }
+
+        let modes = paths
+            .iter()
+            .filter_map(|path| {
+                std::fs::metadata(path)
+                    .ok()
+                    .map(|metadata| (path.clone(), metadata.permissions().mode() & 0o7777))
+            })
+            .collect();
+
+        *self.last_modes.borrow_mut() = Some(modes);
+    }
+}
+
 #[cfg(unix)]
 impl Atom for FilePermissions {
     fn plan(&self) -> bool {
-        let metadata = match std::fs::metadata(&self.path) {
-            Ok(m) => m,
+        let mode_fn = match parse_mode_spec(&self.mode) {
+            Ok(f) => f,
+            Err(err) => {
+                error!("Couldn't parse mode '{}': {}", &self.mode, err);
+
+                return false;
+            }
+        };
+
+        let paths = match self.target_paths() {
+            Ok(paths) => paths,
             Err(err) => {
-                error!(
-                    "Couldn't get metadata for {}, rejecting atom: {}",
-                    &self.path.as_os_str().to_str().unwrap(),
-                    err.to_string()
-                );
+                error!("Couldn't walk {:?}, rejecting atom: {}", &self.path, err);
 
                 return false;
             }
         };
 
-        // We expect permissions to come through as if the user was using chmod themselves.
-        // This means we support 644/755 decimal syntax. We need to add 0o100000 to support
-        // the part of chmod they don't often type.
-        std::fs::Permissions::from_mode(0o100000 + self.mode).mode()
-            != metadata.permissions().mode()
+        self.snapshot_modes(&paths);
+
+        paths.iter().any(|path| match std::fs::metadata(path) {
+            Ok(metadata) => Self::entry_needs_change(&metadata, mode_fn.as_ref()),
+            Err(err) => {
+                error!("Couldn't get metadata for {:?}, rejecting entry: {}", path, err);
+
+                false
+            }
+        })
     }
 
     fn execute(&self) -> anyhow::Result<()> {
-        std::fs::set_permissions(
-            self.path.as_path(),
-            std::fs::Permissions::from_mode(self.mode),
-        )?;
+        let mode_fn = parse_mode_spec(&self.mode)?;
+        let paths = self.target_paths()?;
 
-        return Ok(());
+        self.snapshot_modes(&paths);
+
+        for path in &paths {
+            let metadata = std::fs::metadata(path)?;
+            Self::apply_to_entry(path, &metadata, mode_fn.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn revert(&self) -> anyhow::Result<()> {
+        let last_modes = self.last_modes.borrow();
+
+        let Some(last_modes) = last_modes.as_ref() else {
+            return Ok(());
+        };
+
+        for (path, mode) in last_modes {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(*mode))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -109,14 +440,20 @@ mod tests {
 
         let file_chmod = FilePermissions {
             path: temp_dir.path().join("644"),
-            mode: 0o644,
+            mode: "644".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
         };
 
         assert_eq!(false, file_chmod.plan());
 
         let file_chmod = FilePermissions {
             path: temp_dir.path().join("644"),
-            mode: 0o640,
+            mode: "640".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
         };
 
         assert_eq!(true, file_chmod.plan());
@@ -151,14 +488,20 @@ mod tests {
 
         let file_chmod = FilePermissions {
             path: temp_dir.path().join("644"),
-            mode: 0o644,
+            mode: "644".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
         };
 
         assert_eq!(false, file_chmod.plan());
 
         let file_chmod = FilePermissions {
             path: temp_dir.path().join("644"),
-            mode: 0o640,
+            mode: "640".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
         };
 
         assert_eq!(true, file_chmod.plan());
@@ -167,5 +510,288 @@ mod tests {
     }
 
     #[test]
-    fn it_can_revert() {}
+    fn it_can_revert() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let file_path = temp_dir.path().join("644");
+        std::fs::File::create(&file_path).unwrap();
+
+        assert_eq!(
+            true,
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).is_ok(),
+        );
+
+        let file_chmod = FilePermissions {
+            path: file_path.clone(),
+            mode: "640".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
+        };
+
+        assert_eq!(true, file_chmod.execute().is_ok());
+        assert_eq!(
+            0o640,
+            std::fs::metadata(&file_path).unwrap().permissions().mode() & 0o7777
+        );
+
+        assert_eq!(true, file_chmod.revert().is_ok());
+        assert_eq!(
+            0o644,
+            std::fs::metadata(&file_path).unwrap().permissions().mode() & 0o7777
+        );
+    }
+
+    #[test]
+    fn it_does_not_clobber_the_snapshot_on_a_later_plan() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let file_path = temp_dir.path().join("644");
+        std::fs::File::create(&file_path).unwrap();
+
+        assert_eq!(
+            true,
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).is_ok(),
+        );
+
+        let file_chmod = FilePermissions {
+            path: file_path.clone(),
+            mode: "640".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
+        };
+
+        assert_eq!(true, file_chmod.execute().is_ok());
+
+        // A convergence check re-running plan() after execute() must not
+        // overwrite the snapshot with the mode execute() just applied.
+        assert_eq!(false, file_chmod.plan());
+
+        assert_eq!(true, file_chmod.revert().is_ok());
+        assert_eq!(
+            0o644,
+            std::fs::metadata(&file_path).unwrap().permissions().mode() & 0o7777
+        );
+    }
+
+    #[test]
+    fn it_can_apply_symbolic_additions() {
+        let mode_fn = parse_mode_spec("u+rwx,go-w").unwrap();
+
+        assert_eq!(mode_fn(0o644, false), 0o744);
+    }
+
+    #[test]
+    fn it_can_apply_symbolic_assignment() {
+        let mode_fn = parse_mode_spec("a=r").unwrap();
+
+        assert_eq!(mode_fn(0o755, false), 0o444);
+    }
+
+    #[test]
+    fn it_defaults_to_all_when_who_is_missing() {
+        let mode_fn = parse_mode_spec("+x").unwrap();
+
+        assert_eq!(mode_fn(0o644, false), 0o755);
+    }
+
+    #[test]
+    fn it_still_accepts_plain_octal() {
+        let mode_fn = parse_mode_spec("640").unwrap();
+
+        assert_eq!(mode_fn(0o777, false), 0o640);
+    }
+
+    #[test]
+    fn it_leaves_capital_x_alone_on_a_non_executable_file() {
+        let mode_fn = parse_mode_spec("a+rX").unwrap();
+
+        assert_eq!(mode_fn(0o644, false), 0o644);
+    }
+
+    #[test]
+    fn it_applies_capital_x_on_a_directory() {
+        let mode_fn = parse_mode_spec("a+rX").unwrap();
+
+        assert_eq!(mode_fn(0o644, true), 0o755);
+    }
+
+    #[test]
+    fn it_applies_capital_x_when_already_executable() {
+        let mode_fn = parse_mode_spec("go+X").unwrap();
+
+        assert_eq!(mode_fn(0o744, false), 0o755);
+    }
+
+    #[test]
+    fn it_plans_directories_using_the_directory_type_bits() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        assert_eq!(
+            true,
+            std::fs::set_permissions(temp_dir.path(), std::fs::Permissions::from_mode(0o755))
+                .is_ok(),
+        );
+
+        let dir_chmod = FilePermissions {
+            path: temp_dir.path().to_path_buf(),
+            mode: "755".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
+        };
+
+        assert_eq!(false, dir_chmod.plan());
+
+        let dir_chmod = FilePermissions {
+            path: temp_dir.path().to_path_buf(),
+            mode: "750".to_string(),
+            recursive: false,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
+        };
+
+        assert_eq!(true, dir_chmod.plan());
+    }
+
+    #[test]
+    fn it_can_plan_and_execute_recursively() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::File::create(temp_dir.path().join("a")).unwrap();
+        std::fs::File::create(nested.join("b")).unwrap();
+        std::fs::File::create(nested.join("skip")).unwrap();
+
+        for entry in [
+            temp_dir.path().to_path_buf(),
+            nested.clone(),
+            temp_dir.path().join("a"),
+            nested.join("b"),
+            nested.join("skip"),
+        ] {
+            std::fs::set_permissions(entry, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        let tree_chmod = FilePermissions {
+            path: temp_dir.path().to_path_buf(),
+            mode: "640".to_string(),
+            recursive: true,
+            excludes: vec!["**/skip".to_string()],
+            last_modes: RefCell::new(None),
+        };
+
+        assert_eq!(true, tree_chmod.plan());
+        assert_eq!(true, tree_chmod.execute().is_ok());
+        assert_eq!(false, tree_chmod.plan());
+
+        // Recursive mode must change the root directory itself too, not
+        // just its contents.
+        let root_metadata = std::fs::metadata(temp_dir.path()).unwrap();
+        assert_eq!(0o640, root_metadata.permissions().mode() & 0o7777);
+
+        let skipped = std::fs::metadata(nested.join("skip")).unwrap();
+        assert_eq!(0o644, skipped.permissions().mode() & 0o7777);
+
+        assert_eq!(true, tree_chmod.revert().is_ok());
+
+        for entry in [
+            temp_dir.path().to_path_buf(),
+            nested.clone(),
+            temp_dir.path().join("a"),
+            nested.join("b"),
+        ] {
+            let metadata = std::fs::metadata(&entry).unwrap();
+            assert_eq!(0o644, metadata.permissions().mode() & 0o7777);
+        }
+    }
+
+    #[test]
+    fn it_orders_recursive_targets_children_before_their_directory() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::File::create(nested.join("b")).unwrap();
+
+        let tree_chmod = FilePermissions {
+            path: temp_dir.path().to_path_buf(),
+            mode: "640".to_string(),
+            recursive: true,
+            excludes: vec![],
+            last_modes: RefCell::new(None),
+        };
+
+        let paths = tree_chmod.target_paths().unwrap();
+
+        // A directory must be touched only after everything beneath it, or
+        // tightening its mode mid-walk would lock out the rest of the walk.
+        let index_of = |path: &std::path::Path| paths.iter().position(|p| p == path).unwrap();
+
+        assert!(index_of(&nested.join("b")) < index_of(&nested));
+        assert!(index_of(&nested) < index_of(temp_dir.path()));
+    }
+
+    #[test]
+    fn it_does_not_follow_symlinked_directories_when_walking() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let outside_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+        std::fs::File::create(outside_dir.path().join("secret")).unwrap();
+
+        let link_path = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(outside_dir.path(), &link_path).unwrap();
+
+        let excludes = compile_excludes(&[]).unwrap();
+        let entries = walk_tree(temp_dir.path(), &excludes).unwrap();
+
+        // The symlink itself is a legitimate target, but its target's
+        // contents must not be pulled into this atom's scope.
+        assert_eq!(true, entries.contains(&link_path));
+        assert_eq!(false, entries.contains(&link_path.join("secret")));
+    }
 }
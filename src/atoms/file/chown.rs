@@ -0,0 +1,231 @@
+use super::super::Atom;
+use super::FileAtom;
+use std::path::PathBuf;
+use tracing::error;
+
+pub struct FileOwnership {
+    path: PathBuf,
+    user: Option<String>,
+    group: Option<String>,
+}
+
+impl FileAtom for FileOwnership {
+    fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl std::fmt::Display for FileOwnership {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The ownership of {} needs to be set to {}:{}",
+            self.path.to_str().unwrap(),
+            self.user.as_deref().unwrap_or("-"),
+            self.group.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+// Resolves a `chown`-style user spec (a name or a numeric uid) to a uid.
+#[cfg(unix)]
+fn resolve_uid(user: &str) -> anyhow::Result<u32> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok(uid);
+    }
+
+    let name = CString::new(user)?;
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+
+    if passwd.is_null() {
+        anyhow::bail!("No such user '{}'", user);
+    }
+
+    Ok(unsafe { (*passwd).pw_uid })
+}
+
+// Resolves a `chown`-style group spec (a name or a numeric gid) to a gid.
+#[cfg(unix)]
+fn resolve_gid(group: &str) -> anyhow::Result<u32> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+
+    let name = CString::new(group)?;
+    let grp = unsafe { libc::getgrnam(name.as_ptr()) };
+
+    if grp.is_null() {
+        anyhow::bail!("No such group '{}'", group);
+    }
+
+    Ok(unsafe { (*grp).gr_gid })
+}
+
+#[cfg(unix)]
+impl FileOwnership {
+    // Resolves the requested user/group against the file's current
+    // ownership, so unspecified fields leave that half of the pair alone.
+    fn resolved_target(&self, current_uid: u32, current_gid: u32) -> anyhow::Result<(u32, u32)> {
+        let uid = match &self.user {
+            Some(user) => resolve_uid(user)?,
+            None => current_uid,
+        };
+        let gid = match &self.group {
+            Some(group) => resolve_gid(group)?,
+            None => current_gid,
+        };
+
+        Ok((uid, gid))
+    }
+}
+
+#[cfg(unix)]
+impl Atom for FileOwnership {
+    fn plan(&self) -> bool {
+        // Use lstat, not stat: `execute()` applies ownership via `lchown`
+        // (the link itself, not its target), so `plan()` must read the same
+        // inode or a symlink would never converge.
+        let metadata = match std::fs::symlink_metadata(&self.path) {
+            Ok(m) => m,
+            Err(err) => {
+                error!(
+                    "Couldn't get metadata for {}, rejecting atom: {}",
+                    &self.path.as_os_str().to_str().unwrap(),
+                    err.to_string()
+                );
+
+                return false;
+            }
+        };
+
+        let (target_uid, target_gid) = match self.resolved_target(metadata.uid(), metadata.gid())
+        {
+            Ok(target) => target,
+            Err(err) => {
+                error!("Couldn't resolve ownership for {:?}: {}", &self.path, err);
+
+                return false;
+            }
+        };
+
+        target_uid != metadata.uid() || target_gid != metadata.gid()
+    }
+
+    fn execute(&self) -> anyhow::Result<()> {
+        let metadata = std::fs::symlink_metadata(&self.path)?;
+        let (target_uid, target_gid) = self.resolved_target(metadata.uid(), metadata.gid())?;
+
+        let path = CString::new(self.path.as_os_str().as_bytes())?;
+        let result = unsafe { libc::lchown(path.as_ptr(), target_uid, target_gid) };
+
+        if result != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+impl Atom for FileOwnership {
+    fn plan(&self) -> bool {
+        false
+    }
+
+    fn execute(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn revert(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_can_plan_when_ownership_is_unchanged() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let file_path = temp_dir.path().join("owned");
+        std::fs::File::create(&file_path).unwrap();
+
+        let metadata = std::fs::metadata(&file_path).unwrap();
+
+        let file_chown = FileOwnership {
+            path: file_path,
+            user: Some(metadata.uid().to_string()),
+            group: Some(metadata.gid().to_string()),
+        };
+
+        assert_eq!(false, file_chown.plan());
+    }
+
+    #[test]
+    fn it_can_plan_when_ownership_differs() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        let file_path = temp_dir.path().join("owned");
+        std::fs::File::create(&file_path).unwrap();
+
+        let metadata = std::fs::metadata(&file_path).unwrap();
+
+        let file_chown = FileOwnership {
+            path: file_path,
+            user: Some((metadata.uid() + 1).to_string()),
+            group: None,
+        };
+
+        assert_eq!(true, file_chown.plan());
+    }
+
+    #[test]
+    fn it_plans_a_dangling_symlink_by_its_own_ownership() {
+        let temp_dir = match tempfile::tempdir() {
+            std::result::Result::Ok(dir) => dir,
+            std::result::Result::Err(_) => {
+                assert_eq!(false, true);
+                return;
+            }
+        };
+
+        // A dangling symlink: following it (`std::fs::metadata`) fails with
+        // NotFound, which would make plan() reject the atom (return false)
+        // regardless of the requested ownership. Statting the link itself
+        // (`lstat`, matching `execute()`'s `lchown`) still detects a genuine
+        // mismatch here, so this passes only because plan() stats the link.
+        let link_path = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink(temp_dir.path().join("missing-target"), &link_path).unwrap();
+
+        let link_metadata = std::fs::symlink_metadata(&link_path).unwrap();
+
+        let link_chown = FileOwnership {
+            path: link_path,
+            user: Some((link_metadata.uid() + 1).to_string()),
+            group: None,
+        };
+
+        assert_eq!(true, link_chown.plan());
+    }
+}